@@ -0,0 +1,75 @@
+use std::fmt;
+
+use crate::txn::TxnOpError;
+
+#[derive(Debug)]
+pub enum Error {
+    Message(String),
+    /// `POST /v1/txn` was rejected (HTTP 409) because one or more of the
+    /// submitted operations failed; none of them were applied.
+    TxnRolledBack(Vec<TxnOpError>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Message(msg) => write!(f, "{}", msg),
+            Error::TxnRolledBack(errors) => {
+                write!(f, "transaction rolled back: ")?;
+                for (i, err) in errors.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "op {}: {}", err.OpIndex, err.What)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<&str> for Error {
+    fn from(msg: &str) -> Self {
+        Error::Message(msg.to_owned())
+    }
+}
+
+impl From<String> for Error {
+    fn from(msg: String) -> Self {
+        Error::Message(msg)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Message(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Message(e.to_string())
+    }
+}
+
+impl From<base64::DecodeError> for Error {
+    fn from(e: base64::DecodeError) -> Self {
+        Error::Message(e.to_string())
+    }
+}
+
+impl From<std::str::Utf8Error> for Error {
+    fn from(e: std::str::Utf8Error) -> Self {
+        Error::Message(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Message(e.to_string())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
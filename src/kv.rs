@@ -1,24 +1,25 @@
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::rc::Rc;
 
 use crate::errors::Error;
 use crate::errors::Result;
-use crate::request::{delete, get, get_vec, put};
+use crate::request::{delete, get, get_vec, put, put_bytes};
 use crate::{Client, QueryMeta, QueryOptions, WriteMeta, WriteOptions};
 
 use serde::de::DeserializeOwned;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[serde(default)]
 #[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
-struct KVResult {
-    Key: String,
-    CreateIndex: Option<u64>,
-    ModifyIndex: Option<u64>,
-    LockIndex: Option<u64>,
-    Flags: Option<u64>,
-    Value: String,
-    Session: Option<String>,
+#[serde(default)]
+pub(crate) struct KVResult {
+    pub(crate) Key: String,
+    pub(crate) CreateIndex: Option<u64>,
+    pub(crate) ModifyIndex: Option<u64>,
+    pub(crate) LockIndex: Option<u64>,
+    pub(crate) Flags: Option<u64>,
+    pub(crate) Value: String,
+    pub(crate) Session: Option<String>,
 }
 
 #[derive(Clone, Default)]
@@ -33,14 +34,15 @@ pub struct KVPair<T> {
 }
 
 
-impl<T> From<KVResult> for KVPair<T> where T: DeserializeOwned {
-    fn from(result: KVResult) -> KVPair<T> {
+impl<T> TryFrom<KVResult> for KVPair<T> where T: DeserializeOwned {
+    type Error = Error;
 
-        let bytes = base64::decode(&result.Value).unwrap();
-        let decoded = std::str::from_utf8(&bytes).unwrap();
-        let value = serde_json::from_str(&decoded).unwrap();
+    fn try_from(result: KVResult) -> Result<KVPair<T>> {
+        let bytes = base64::decode(&result.Value)?;
+        let decoded = std::str::from_utf8(&bytes)?;
+        let value = serde_json::from_str(decoded)?;
 
-        KVPair {
+        Ok(KVPair {
             Key: result.Key,
             CreateIndex: result.CreateIndex,
             ModifyIndex: result.ModifyIndex,
@@ -48,10 +50,35 @@ impl<T> From<KVResult> for KVPair<T> where T: DeserializeOwned {
             Flags: result.Flags,
             Value: Rc::new(value),
             Session: result.Session,
-        }
+        })
     }
 }
 
+/// Given the index we last polled with (`baseline`) and the index Consul
+/// just returned (`current`), decides whether `watch_get` has a change to
+/// report. A `current` lower than `baseline` means the index space was
+/// reset server-side, so treat that like starting over from 0 rather than
+/// looping forever waiting for an index that will never come back around.
+fn rebase_index(baseline: u64, current: u64) -> (u64, bool) {
+    let baseline = if current < baseline { 0 } else { baseline };
+    (baseline, current != baseline)
+}
+
+/// Decodes a `KVResult` into its raw bytes without attempting to parse them
+/// as JSON, for values that are opaque blobs rather than serialized `T`s.
+fn kv_result_into_raw(result: KVResult) -> Result<KVPair<Vec<u8>>> {
+    let bytes = base64::decode(&result.Value)?;
+    Ok(KVPair {
+        Key: result.Key,
+        CreateIndex: result.CreateIndex,
+        ModifyIndex: result.ModifyIndex,
+        LockIndex: result.LockIndex,
+        Flags: result.Flags,
+        Value: Rc::new(bytes),
+        Session: result.Session,
+    })
+}
+
 pub trait KV {
 
     fn acquire<T: Serialize>(
@@ -64,7 +91,25 @@ pub trait KV {
 
     fn get<T: DeserializeOwned>(&self, _: &str, _: Option<&QueryOptions>) -> Result<(Option<KVPair<T>>, QueryMeta)>;
 
-    //fn list<T: DeserializeOwned>(&self, _: &str, _: Option<&QueryOptions>) -> Result<(Vec<KVPair<T>>, QueryMeta)>;
+    /// Like `get`, but returns the value's raw bytes instead of trying to
+    /// parse them as JSON, so blobs, protobuf, or plain strings come back
+    /// as bytes instead of as a decode error.
+    fn get_raw(&self, _: &str, _: Option<&QueryOptions>) -> Result<(Option<KVPair<Vec<u8>>>, QueryMeta)>;
+
+    fn list<T: DeserializeOwned>(&self, _: &str, _: Option<&QueryOptions>) -> Result<(Vec<KVPair<T>>, QueryMeta)>;
+
+    fn keys(&self, _: &str, _: &str, _: Option<&QueryOptions>) -> Result<(Vec<String>, QueryMeta)>;
+
+    /// Blocks until `key` changes from the value last observed at
+    /// `last_index`, then returns the new value. Callers drive reactive
+    /// reloads by looping: keep calling `watch_get` with the `ModifyIndex`
+    /// (or `QueryMeta::last_index`) returned by the previous call.
+    fn watch_get<T: DeserializeOwned>(
+        &self,
+        _: &str,
+        _: u64,
+        _: Option<&QueryOptions>,
+    ) -> Result<(Option<KVPair<T>>, QueryMeta)>;
 
     fn put<T: Serialize>(
         &self,
@@ -72,6 +117,32 @@ pub trait KV {
         _: Option<&WriteOptions>,
     ) -> Result<(bool, WriteMeta)>;
 
+    /// Like `put`, but only applies if `pair.ModifyIndex` still matches the
+    /// value currently stored in Consul (a missing `ModifyIndex` is treated
+    /// as `0`, i.e. "create only if absent"). Returns `false` without error
+    /// when the index no longer matches.
+    fn cas_put<T: Serialize>(
+        &self,
+        _: &KVPair<T>,
+        _: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)>;
+
+    /// Like `put`, but writes `pair.Value`'s bytes as-is instead of
+    /// JSON-encoding them.
+    fn put_raw(
+        &self,
+        _: &KVPair<Vec<u8>>,
+        _: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)>;
+
+    /// Like `delete`, but only applies if `pair.ModifyIndex` still matches
+    /// the value currently stored in Consul.
+    fn cas_delete<T>(
+        &self,
+        _: &KVPair<T>,
+        _: Option<&WriteOptions>,
+    ) -> Result<(bool, WriteMeta)>;
+
     fn release<T: Serialize>(
         &self,
         _: &KVPair<T>,
@@ -93,7 +164,7 @@ impl KV for Client {
         if let Some(ref session) = pair.Session {
             params.insert(String::from("acquire"), session.to_owned());
             let path = format!("/v1/kv/{}", pair.Key);
-            put(&path, Some(&pair.Value), &self.config, params, o)
+            put(&path, Some(&pair.Value), self, params, o)
         } else {
             Err(Error::from("Session flag is required to acquire lock"))
         }
@@ -101,7 +172,7 @@ impl KV for Client {
 
     fn delete(&self, key: &str, options: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
         let path = format!("/v1/kv/{}", key);
-        delete(&path, &self.config, HashMap::new(), options)
+        delete(&path, self, HashMap::new(), options)
     }
 
     fn get<T>(
@@ -112,34 +183,78 @@ impl KV for Client {
         where T: DeserializeOwned
     {
         let path = format!("/v1/kv/{}", key);
-        let response: Result<(Vec<KVResult>, QueryMeta)> = get(&path, &self.config, HashMap::new(), options);
-        response.map(|(results, meta)| {
-            let maybe_first = results.first();
-            let kv_pair = maybe_first.map(|kv_result| {
-                let owned_result = kv_result.clone();
-                owned_result.into()
-            });
+        let (results, meta): (Vec<KVResult>, QueryMeta) = get(&path, self, HashMap::new(), options)?;
+        let kv_pair = match results.into_iter().next() {
+            Some(kv_result) => Some(KVPair::try_from(kv_result)?),
+            None => None,
+        };
 
+        Ok((kv_pair, meta))
+    }
 
-            (kv_pair, meta)
-        })
+    fn get_raw(
+        &self,
+        key: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Option<KVPair<Vec<u8>>>, QueryMeta)> {
+        let path = format!("/v1/kv/{}", key);
+        let (results, meta): (Vec<KVResult>, QueryMeta) = get(&path, self, HashMap::new(), options)?;
+        let kv_pair = match results.into_iter().next() {
+            Some(kv_result) => Some(kv_result_into_raw(kv_result)?),
+            None => None,
+        };
+
+        Ok((kv_pair, meta))
     }
 
-    //fn list<T>(&self, prefix: &str, o: Option<&QueryOptions>) -> Result<(Vec<KVPair<T>>, QueryMeta)>
-    //    where T: DeserializeOwned
-    //{
-    //    let mut params = HashMap::new();
-    //    params.insert(String::from("recurse"), String::from(""));
-    //    let path = format!("/v1/kv/{}", prefix);
-    //    let response: Result<(Vec<KVResult>, QueryMeta)> = get(&path, &self.config, params, o);
-    //    response.map(|(results, meta)| {
-    //        let key_values = results.iter()
-    //            .map(|el| el.clone().into())
-    //            .collect();
+    fn list<T>(&self, prefix: &str, o: Option<&QueryOptions>) -> Result<(Vec<KVPair<T>>, QueryMeta)>
+        where T: DeserializeOwned
+    {
+        let mut params = HashMap::new();
+        params.insert(String::from("recurse"), String::from(""));
+        let path = format!("/v1/kv/{}", prefix);
+        let (results, meta): (Vec<KVResult>, QueryMeta) = get_vec(&path, self, params, o)?;
+        let key_values = results.into_iter()
+            .map(KVPair::try_from)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok((key_values, meta))
+    }
 
-    //        (key_values, meta)
-    //    })
-    //}
+    fn keys(&self, prefix: &str, separator: &str, o: Option<&QueryOptions>) -> Result<(Vec<String>, QueryMeta)> {
+        let mut params = HashMap::new();
+        params.insert(String::from("keys"), String::from(""));
+        if !separator.is_empty() {
+            params.insert(String::from("separator"), separator.to_owned());
+        }
+        let path = format!("/v1/kv/{}", prefix);
+        // The `keys` endpoint responds with a bare JSON array of strings
+        // rather than `KVResult` objects, so it can't go through the
+        // `KVResult -> KVPair` conversion `list` relies on.
+        get(&path, self, params, o)
+    }
+
+    fn watch_get<T>(
+        &self,
+        key: &str,
+        last_index: u64,
+        o: Option<&QueryOptions>,
+    ) -> Result<(Option<KVPair<T>>, QueryMeta)>
+        where T: DeserializeOwned
+    {
+        let mut baseline = last_index;
+        loop {
+            let mut options = o.cloned().unwrap_or_default();
+            options.index = Some(baseline);
+            let (pair, meta) = self.get::<T>(key, Some(&options))?;
+            let current = meta.last_index.unwrap_or(baseline);
+            let changed;
+            (baseline, changed) = rebase_index(baseline, current);
+            if changed {
+                return Ok((pair, meta));
+            }
+        }
+    }
 
     fn put<T>(&self, pair: &KVPair<T>, o: Option<&WriteOptions>) -> Result<(bool, WriteMeta)>
     where
@@ -152,7 +267,40 @@ impl KV for Client {
             }
         }
         let path = format!("/v1/kv/{}", pair.Key);
-        put(&path, Some(&pair.Value), &self.config, params, o)
+        put(&path, Some(&pair.Value), self, params, o)
+    }
+
+    fn cas_put<T>(&self, pair: &KVPair<T>, o: Option<&WriteOptions>) -> Result<(bool, WriteMeta)>
+    where
+        T: Serialize,
+    {
+        let mut params = HashMap::new();
+        if let Some(i) = pair.Flags {
+            if i != 0 {
+                params.insert(String::from("flags"), i.to_string());
+            }
+        }
+        params.insert(String::from("cas"), cas_index_param(pair.ModifyIndex));
+        let path = format!("/v1/kv/{}", pair.Key);
+        put(&path, Some(&pair.Value), self, params, o)
+    }
+
+    fn put_raw(&self, pair: &KVPair<Vec<u8>>, o: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
+        let mut params = HashMap::new();
+        if let Some(i) = pair.Flags {
+            if i != 0 {
+                params.insert(String::from("flags"), i.to_string());
+            }
+        }
+        let path = format!("/v1/kv/{}", pair.Key);
+        put_bytes(&path, &pair.Value, self, params, o)
+    }
+
+    fn cas_delete<T>(&self, pair: &KVPair<T>, o: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
+        let mut params = HashMap::new();
+        params.insert(String::from("cas"), cas_index_param(pair.ModifyIndex));
+        let path = format!("/v1/kv/{}", pair.Key);
+        delete(&path, self, params, o)
     }
 
     fn release<T>(&self, pair: &KVPair<T>, o: Option<&WriteOptions>) -> Result<(bool, WriteMeta)>
@@ -168,9 +316,80 @@ impl KV for Client {
         if let Some(ref session) = pair.Session {
             params.insert(String::from("release"), session.to_owned());
             let path = format!("/v1/kv/{}", pair.Key);
-            put(&path, Some(&pair.Value), &self.config, params, o)
+            put(&path, Some(&pair.Value), self, params, o)
         } else {
             Err(Error::from("Session flag is required to release a lock"))
         }
     }
 }
+
+/// The `cas` query-parameter value for a CAS write: a missing `ModifyIndex`
+/// means "create only if the key doesn't already exist", which Consul spells
+/// as index `0`.
+fn cas_index_param(modify_index: Option<u64>) -> String {
+    modify_index.unwrap_or(0).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn try_from_decodes_base64_json_value() {
+        let result = KVResult {
+            Key: String::from("foo"),
+            CreateIndex: Some(1),
+            ModifyIndex: Some(2),
+            LockIndex: None,
+            Flags: None,
+            Value: base64::encode("\"bar\""),
+            Session: None,
+        };
+        let pair = KVPair::<String>::try_from(result).unwrap();
+        assert_eq!(*pair.Value, "bar");
+        assert_eq!(pair.Key, "foo");
+        assert_eq!(pair.ModifyIndex, Some(2));
+    }
+
+    #[test]
+    fn kv_result_into_raw_skips_json_decoding() {
+        let result = KVResult {
+            Key: String::from("foo"),
+            CreateIndex: None,
+            ModifyIndex: None,
+            LockIndex: None,
+            Flags: None,
+            Value: base64::encode(b"\x00\x01not json"),
+            Session: None,
+        };
+        let pair = kv_result_into_raw(result).unwrap();
+        assert_eq!(*pair.Value, b"\x00\x01not json".to_vec());
+    }
+
+    #[test]
+    fn rebase_index_reports_no_change_when_index_is_unchanged() {
+        let (baseline, changed) = rebase_index(5, 5);
+        assert_eq!(baseline, 5);
+        assert!(!changed);
+    }
+
+    #[test]
+    fn rebase_index_reports_change_on_higher_index() {
+        let (baseline, changed) = rebase_index(5, 6);
+        assert_eq!(baseline, 5);
+        assert!(changed);
+    }
+
+    #[test]
+    fn rebase_index_resets_baseline_to_zero_when_index_space_resets() {
+        let (baseline, changed) = rebase_index(100, 3);
+        assert_eq!(baseline, 0);
+        assert!(changed);
+    }
+
+    #[test]
+    fn cas_index_param_defaults_missing_modify_index_to_zero() {
+        assert_eq!(cas_index_param(None), "0");
+        assert_eq!(cas_index_param(Some(42)), "42");
+    }
+}
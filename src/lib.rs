@@ -0,0 +1,98 @@
+// Consul's JSON fields are PascalCase; mirroring them directly on our
+// structs keeps (de)serialization free of `#[serde(rename)]` boilerplate.
+#![allow(non_snake_case)]
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub mod errors;
+pub mod kv;
+pub mod lock;
+mod request;
+pub mod session;
+pub mod txn;
+
+use errors::Result;
+
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    pub address: String,
+    pub datacenter: Option<String>,
+    /// ACL token attached to every request as the `X-Consul-Token` header.
+    pub token: Option<String>,
+    /// Path to a PEM-encoded CA bundle used to verify the agent's
+    /// certificate, for talking to an agent with a private CA.
+    pub ca_cert: Option<PathBuf>,
+    /// Path to a PEM-encoded client certificate, for mutual TLS.
+    pub client_cert: Option<PathBuf>,
+    /// Path to the PEM-encoded private key matching `client_cert`.
+    pub client_key: Option<PathBuf>,
+    /// Skip verifying the agent's certificate chain entirely. Only ever
+    /// useful against a local dev agent; never enable this in production.
+    pub tls_skip_verify: bool,
+}
+
+impl Config {
+    pub fn new() -> Result<Config> {
+        Ok(Config {
+            address: String::from("http://127.0.0.1:8500"),
+            ..Config::default()
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct Client {
+    pub config: Config,
+    pub(crate) http_client: reqwest::blocking::Client,
+}
+
+impl Client {
+    pub fn new(config: Config) -> Result<Client> {
+        let mut builder = reqwest::blocking::Client::builder()
+            .danger_accept_invalid_certs(config.tls_skip_verify);
+
+        if let Some(ca_cert) = &config.ca_cert {
+            let ca_pem = fs::read(ca_cert)?;
+            builder = builder.add_root_certificate(reqwest::Certificate::from_pem(&ca_pem)?);
+        }
+
+        if let (Some(client_cert), Some(client_key)) = (&config.client_cert, &config.client_key) {
+            let mut identity_pem = fs::read(client_cert)?;
+            identity_pem.extend_from_slice(&fs::read(client_key)?);
+            builder = builder.identity(reqwest::Identity::from_pem(&identity_pem)?);
+        }
+
+        let http_client = builder.build()?;
+        Ok(Client { config, http_client })
+    }
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct QueryOptions {
+    pub datacenter: Option<String>,
+    /// The `ModifyIndex` last seen by the caller. Passed to Consul as the
+    /// blocking-query `index` parameter so the agent only replies once the
+    /// value has changed (or `wait` elapses).
+    pub index: Option<u64>,
+    /// How long the agent should hold a blocking query open before replying
+    /// with the unchanged value. Only meaningful together with `index`.
+    pub wait_time: Option<Duration>,
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct QueryMeta {
+    pub last_index: Option<u64>,
+    pub request_time: Duration,
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct WriteOptions {
+    pub datacenter: Option<String>,
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct WriteMeta {
+    pub request_time: Duration,
+}
@@ -0,0 +1,82 @@
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::errors::Result;
+use crate::kv::{KVPair, KV};
+use crate::{Client, QueryOptions};
+
+/// A held distributed lock on a single key. Calls `KV::release` when
+/// dropped, so callers get the lock back without having to remember to
+/// release it on every return path.
+pub struct LockGuard<'a> {
+    client: &'a Client,
+    pair: KVPair<String>,
+}
+
+impl<'a> Drop for LockGuard<'a> {
+    fn drop(&mut self) {
+        let _ = self.client.release(&self.pair, None);
+    }
+}
+
+/// Leader-election helper built on a KV key and a Consul session. Acquiring
+/// the lock just means winning `KV::acquire` on `key` with a given session;
+/// `Lock` wraps the acquire/watch/release dance so callers don't have to
+/// reimplement it.
+pub struct Lock<'a> {
+    client: &'a Client,
+    key: String,
+}
+
+impl<'a> Lock<'a> {
+    pub fn new(client: &'a Client, key: &str) -> Lock<'a> {
+        Lock {
+            client,
+            key: key.to_owned(),
+        }
+    }
+
+    /// Attempts to acquire the lock once, without waiting for a rival
+    /// holder to give it up.
+    pub fn try_lock(&self, session: &str) -> Result<Option<LockGuard<'a>>> {
+        let pair = KVPair {
+            Key: self.key.clone(),
+            CreateIndex: None,
+            ModifyIndex: None,
+            LockIndex: None,
+            Flags: None,
+            Value: Rc::new(String::new()),
+            Session: Some(session.to_owned()),
+        };
+        let (acquired, _meta) = self.client.acquire(&pair, None)?;
+        if acquired {
+            Ok(Some(LockGuard {
+                client: self.client,
+                pair,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Blocks until this session wins the lock, waiting on the key (via the
+    /// blocking-query/watch mechanism) between attempts instead of
+    /// busy-polling, so the caller notices as soon as the current holder's
+    /// session releases or expires.
+    pub fn lock(&self, session: &str) -> Result<LockGuard<'a>> {
+        let mut last_index = 0;
+        loop {
+            if let Some(guard) = self.try_lock(session)? {
+                return Ok(guard);
+            }
+
+            let options = QueryOptions {
+                index: Some(last_index),
+                wait_time: Some(Duration::from_secs(5 * 60)),
+                ..QueryOptions::default()
+            };
+            let (_pair, meta) = self.client.watch_get::<String>(&self.key, last_index, Some(&options))?;
+            last_index = meta.last_index.unwrap_or(last_index);
+        }
+    }
+}
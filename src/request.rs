@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::errors::Result;
+use crate::{Client, QueryMeta, QueryOptions, WriteMeta, WriteOptions};
+
+fn consul_index(response: &reqwest::blocking::Response) -> Option<u64> {
+    response
+        .headers()
+        .get("X-Consul-Index")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+}
+
+fn build_url(client: &Client, path: &str, params: &HashMap<String, String>) -> String {
+    let mut url = reqwest::Url::parse(&format!("{}{}", client.config.address, path))
+        .expect("invalid consul address/path");
+    {
+        let mut query = url.query_pairs_mut();
+        for (key, value) in params {
+            query.append_pair(key, value);
+        }
+    }
+    url.into()
+}
+
+/// Attaches the configured ACL token, if any, as the `X-Consul-Token`
+/// header rather than a `token` query parameter, so it doesn't end up in
+/// proxy/access logs or `Referer` headers.
+fn with_token(
+    request: reqwest::blocking::RequestBuilder,
+    client: &Client,
+) -> reqwest::blocking::RequestBuilder {
+    match &client.config.token {
+        Some(token) => request.header("X-Consul-Token", token),
+        None => request,
+    }
+}
+
+fn apply_query_options(params: &mut HashMap<String, String>, client: &Client, options: Option<&QueryOptions>) {
+    let datacenter = options
+        .and_then(|o| o.datacenter.clone())
+        .or_else(|| client.config.datacenter.clone());
+    if let Some(dc) = datacenter {
+        params.insert(String::from("dc"), dc);
+    }
+    if let Some(options) = options {
+        if let Some(index) = options.index {
+            params.insert(String::from("index"), index.to_string());
+        }
+        if let Some(wait_time) = options.wait_time {
+            params.insert(String::from("wait"), format_wait_time(wait_time));
+        }
+    }
+}
+
+/// Renders a blocking-query timeout the way Consul expects it, e.g. `5m0s`.
+fn format_wait_time(wait_time: Duration) -> String {
+    let total_secs = wait_time.as_secs();
+    format!("{}m{}s", total_secs / 60, total_secs % 60)
+}
+
+/// Decodes a write endpoint's boolean response body, treating a body that
+/// fails to parse as a boolean as failure (`false`) rather than success —
+/// a malformed/unexpected body should never look like a write landed.
+fn decode_write_result(body: &str) -> bool {
+    serde_json::from_str::<bool>(body).unwrap_or(false)
+}
+
+fn apply_write_options(params: &mut HashMap<String, String>, client: &Client, options: Option<&WriteOptions>) {
+    let datacenter = options
+        .and_then(|o| o.datacenter.clone())
+        .or_else(|| client.config.datacenter.clone());
+    if let Some(dc) = datacenter {
+        params.insert(String::from("dc"), dc);
+    }
+}
+
+pub fn get<T: DeserializeOwned>(
+    path: &str,
+    client: &Client,
+    mut params: HashMap<String, String>,
+    options: Option<&QueryOptions>,
+) -> Result<(T, QueryMeta)> {
+    apply_query_options(&mut params, client, options);
+    let start = Instant::now();
+    let url = build_url(client, path, &params);
+    let response = with_token(client.http_client.get(&url), client).send()?;
+
+    // Consul responds 404 (rather than 200 with an empty array) when a key
+    // doesn't exist, e.g. because it was deleted. Every caller of `get`
+    // expects the "missing" case as an empty list, not a hard error, so
+    // that `KV::get`/`watch_get` can represent it as `Ok((None, _))`.
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        let meta = QueryMeta {
+            last_index: consul_index(&response),
+            request_time: start.elapsed(),
+        };
+        let value = serde_json::from_str("[]")?;
+        return Ok((value, meta));
+    }
+
+    let response = response.error_for_status()?;
+    let meta = QueryMeta {
+        last_index: consul_index(&response),
+        request_time: start.elapsed(),
+    };
+    let value = response.json::<T>()?;
+    Ok((value, meta))
+}
+
+pub fn get_vec<T: DeserializeOwned>(
+    path: &str,
+    client: &Client,
+    params: HashMap<String, String>,
+    options: Option<&QueryOptions>,
+) -> Result<(Vec<T>, QueryMeta)> {
+    get::<Vec<T>>(path, client, params, options)
+}
+
+pub fn put<T: Serialize>(
+    path: &str,
+    body: Option<&T>,
+    client: &Client,
+    mut params: HashMap<String, String>,
+    options: Option<&WriteOptions>,
+) -> Result<(bool, WriteMeta)> {
+    apply_write_options(&mut params, client, options);
+    let start = Instant::now();
+    let url = build_url(client, path, &params);
+    let mut request = with_token(client.http_client.put(&url), client);
+    if let Some(body) = body {
+        request = request.json(body);
+    }
+    let response = request.send()?.error_for_status()?;
+    let meta = WriteMeta {
+        request_time: start.elapsed(),
+    };
+    let ok = decode_write_result(&response.text()?);
+    Ok((ok, meta))
+}
+
+/// Like `put`, but decodes the response body as `R` instead of assuming a
+/// bare boolean, for endpoints (e.g. session create/renew) that reply with
+/// a JSON object or array.
+pub fn put_decode<B: Serialize, R: DeserializeOwned>(
+    path: &str,
+    body: Option<&B>,
+    client: &Client,
+    mut params: HashMap<String, String>,
+    options: Option<&WriteOptions>,
+) -> Result<(R, WriteMeta)> {
+    apply_write_options(&mut params, client, options);
+    let start = Instant::now();
+    let url = build_url(client, path, &params);
+    let mut request = with_token(client.http_client.put(&url), client);
+    if let Some(body) = body {
+        request = request.json(body);
+    }
+    let response = request.send()?.error_for_status()?;
+    let meta = WriteMeta {
+        request_time: start.elapsed(),
+    };
+    let value = response.json::<R>()?;
+    Ok((value, meta))
+}
+
+/// Like `put_decode`, but treats a 404 response as "the resource doesn't
+/// exist" rather than an error, for endpoints (e.g. session renew) where a
+/// missing ID is an expected, recoverable outcome rather than a failure.
+pub fn put_decode_opt<B: Serialize, R: DeserializeOwned>(
+    path: &str,
+    body: Option<&B>,
+    client: &Client,
+    mut params: HashMap<String, String>,
+    options: Option<&WriteOptions>,
+) -> Result<(Option<R>, WriteMeta)> {
+    apply_write_options(&mut params, client, options);
+    let start = Instant::now();
+    let url = build_url(client, path, &params);
+    let mut request = with_token(client.http_client.put(&url), client);
+    if let Some(body) = body {
+        request = request.json(body);
+    }
+    let response = request.send()?;
+    let meta = WriteMeta {
+        request_time: start.elapsed(),
+    };
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok((None, meta));
+    }
+    let value = response.error_for_status()?.json::<R>()?;
+    Ok((Some(value), meta))
+}
+
+/// Issues a `POST` and hands back the raw status and body instead of
+/// asserting a 2xx status, so callers that need to interpret a specific
+/// non-2xx response (e.g. the 409 transaction-rollback body) can do so
+/// themselves.
+pub fn post_raw<B: Serialize>(
+    path: &str,
+    body: &B,
+    client: &Client,
+    mut params: HashMap<String, String>,
+    options: Option<&WriteOptions>,
+) -> Result<(reqwest::StatusCode, String, WriteMeta)> {
+    apply_write_options(&mut params, client, options);
+    let start = Instant::now();
+    let url = build_url(client, path, &params);
+    let response = with_token(client.http_client.post(&url), client).json(body).send()?;
+    let status = response.status();
+    let meta = WriteMeta {
+        request_time: start.elapsed(),
+    };
+    let body = response.text()?;
+    Ok((status, body, meta))
+}
+
+/// Like `put`, but sends `body` as-is rather than JSON-encoding it, for
+/// values that are already opaque bytes.
+pub fn put_bytes(
+    path: &str,
+    body: &[u8],
+    client: &Client,
+    mut params: HashMap<String, String>,
+    options: Option<&WriteOptions>,
+) -> Result<(bool, WriteMeta)> {
+    apply_write_options(&mut params, client, options);
+    let start = Instant::now();
+    let url = build_url(client, path, &params);
+    let response = with_token(client.http_client.put(&url), client)
+        .body(body.to_vec())
+        .send()?
+        .error_for_status()?;
+    let meta = WriteMeta {
+        request_time: start.elapsed(),
+    };
+    let ok = decode_write_result(&response.text()?);
+    Ok((ok, meta))
+}
+
+pub fn delete(
+    path: &str,
+    client: &Client,
+    mut params: HashMap<String, String>,
+    options: Option<&WriteOptions>,
+) -> Result<(bool, WriteMeta)> {
+    apply_write_options(&mut params, client, options);
+    let start = Instant::now();
+    let url = build_url(client, path, &params);
+    let response = with_token(client.http_client.delete(&url), client).send()?.error_for_status()?;
+    let meta = WriteMeta {
+        request_time: start.elapsed(),
+    };
+    let ok = decode_write_result(&response.text()?);
+    Ok((ok, meta))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_wait_time_renders_minutes_and_seconds() {
+        assert_eq!(format_wait_time(Duration::from_secs(300)), "5m0s");
+        assert_eq!(format_wait_time(Duration::from_secs(90)), "1m30s");
+        assert_eq!(format_wait_time(Duration::from_secs(5)), "0m5s");
+    }
+
+    #[test]
+    fn decode_write_result_parses_a_valid_boolean_body() {
+        assert!(decode_write_result("true"));
+        assert!(!decode_write_result("false"));
+    }
+
+    #[test]
+    fn decode_write_result_treats_an_unparseable_body_as_failure() {
+        assert!(!decode_write_result(""));
+        assert!(!decode_write_result("<html>not json</html>"));
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::Result;
+use crate::request::{put, put_decode, put_decode_opt};
+use crate::{Client, WriteMeta, WriteOptions};
+
+#[derive(Clone, Default, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct SessionEntry {
+    pub ID: Option<String>,
+    pub Name: Option<String>,
+    pub Node: Option<String>,
+    pub LockDelay: Option<u64>,
+    pub Behavior: Option<String>,
+    pub TTL: Option<String>,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+struct SessionId {
+    #[serde(rename = "ID")]
+    id: String,
+}
+
+pub trait Sessions {
+    fn create(&self, _: &SessionEntry, _: Option<&WriteOptions>) -> Result<(String, WriteMeta)>;
+
+    fn renew(&self, _: &str, _: Option<&WriteOptions>) -> Result<(Option<SessionEntry>, WriteMeta)>;
+
+    fn destroy(&self, _: &str, _: Option<&WriteOptions>) -> Result<(bool, WriteMeta)>;
+}
+
+impl Sessions for Client {
+    fn create(&self, entry: &SessionEntry, o: Option<&WriteOptions>) -> Result<(String, WriteMeta)> {
+        let (created, meta): (SessionId, WriteMeta) =
+            put_decode("/v1/session/create", Some(entry), self, HashMap::new(), o)?;
+        Ok((created.id, meta))
+    }
+
+    fn renew(&self, id: &str, o: Option<&WriteOptions>) -> Result<(Option<SessionEntry>, WriteMeta)> {
+        let path = format!("/v1/session/renew/{}", id);
+        // Consul replies 404 once the session has already expired; that's
+        // an expected, recoverable outcome for the caller, not an error.
+        let (entries, meta): (Option<Vec<SessionEntry>>, WriteMeta) =
+            put_decode_opt::<(), _>(&path, None, self, HashMap::new(), o)?;
+        Ok((entries.and_then(|e| e.into_iter().next()), meta))
+    }
+
+    fn destroy(&self, id: &str, o: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
+        let path = format!("/v1/session/destroy/{}", id);
+        put(&path, None::<&()>, self, HashMap::new(), o)
+    }
+}
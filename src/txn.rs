@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{Error, Result};
+use crate::kv::{KVPair, KVResult};
+use crate::request::post_raw;
+use crate::{Client, WriteMeta, WriteOptions};
+
+#[derive(Clone, Debug, Serialize)]
+#[allow(non_snake_case)]
+struct TxnKVOp {
+    Verb: &'static str,
+    Key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    Value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    Index: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    Session: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[allow(non_snake_case)]
+struct TxnOp {
+    KV: TxnKVOp,
+}
+
+/// A single `KV` result produced by a committed transaction.
+#[derive(Clone, Debug, Deserialize)]
+#[allow(non_snake_case)]
+struct TxnResultEntry {
+    KV: KVResult,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct TxnSuccess {
+    #[serde(default)]
+    Results: Vec<TxnResultEntry>,
+}
+
+/// One failed operation from a rolled-back transaction, identifying which
+/// operation (by its position in the `Txn`) caused the failure and why.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TxnOpError {
+    pub OpIndex: usize,
+    pub What: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct TxnFailure {
+    #[serde(default)]
+    Errors: Vec<TxnOpError>,
+}
+
+/// Builds a sequence of KV operations to submit atomically to
+/// `POST /v1/txn`. Consul either applies every operation or none of them.
+#[derive(Clone, Debug, Default)]
+pub struct Txn {
+    ops: Vec<TxnOp>,
+}
+
+impl Txn {
+    pub fn new() -> Txn {
+        Txn::default()
+    }
+
+    /// Unconditionally sets `key` to `value`.
+    pub fn set<T: Serialize>(mut self, key: &str, value: &T) -> Result<Txn> {
+        self.ops.push(TxnOp {
+            KV: TxnKVOp {
+                Verb: "set",
+                Key: key.to_owned(),
+                Value: Some(encode_value(value)?),
+                Index: None,
+                Session: None,
+            },
+        });
+        Ok(self)
+    }
+
+    /// Sets `key` to `value` only if its `ModifyIndex` still matches `index`.
+    pub fn cas<T: Serialize>(mut self, key: &str, value: &T, index: u64) -> Result<Txn> {
+        self.ops.push(TxnOp {
+            KV: TxnKVOp {
+                Verb: "cas",
+                Key: key.to_owned(),
+                Value: Some(encode_value(value)?),
+                Index: Some(index),
+                Session: None,
+            },
+        });
+        Ok(self)
+    }
+
+    /// Reads the current value of `key` as part of the transaction.
+    pub fn get(mut self, key: &str) -> Txn {
+        self.ops.push(TxnOp {
+            KV: TxnKVOp {
+                Verb: "get",
+                Key: key.to_owned(),
+                Value: None,
+                Index: None,
+                Session: None,
+            },
+        });
+        self
+    }
+
+    /// Deletes `key`.
+    pub fn delete(mut self, key: &str) -> Txn {
+        self.ops.push(TxnOp {
+            KV: TxnKVOp {
+                Verb: "delete",
+                Key: key.to_owned(),
+                Value: None,
+                Index: None,
+                Session: None,
+            },
+        });
+        self
+    }
+
+    /// Deletes every key under the `prefix` subtree.
+    pub fn delete_tree(mut self, prefix: &str) -> Txn {
+        self.ops.push(TxnOp {
+            KV: TxnKVOp {
+                Verb: "delete-tree",
+                Key: prefix.to_owned(),
+                Value: None,
+                Index: None,
+                Session: None,
+            },
+        });
+        self
+    }
+
+    /// Acquires the lock on `key` for `session`.
+    pub fn lock(mut self, key: &str, session: &str) -> Txn {
+        self.ops.push(TxnOp {
+            KV: TxnKVOp {
+                Verb: "lock",
+                Key: key.to_owned(),
+                Value: None,
+                Index: None,
+                Session: Some(session.to_owned()),
+            },
+        });
+        self
+    }
+
+    /// Releases the lock on `key` held by `session`.
+    pub fn unlock(mut self, key: &str, session: &str) -> Txn {
+        self.ops.push(TxnOp {
+            KV: TxnKVOp {
+                Verb: "unlock",
+                Key: key.to_owned(),
+                Value: None,
+                Index: None,
+                Session: Some(session.to_owned()),
+            },
+        });
+        self
+    }
+
+    /// Fails the whole transaction unless `key`'s `ModifyIndex` equals `index`,
+    /// without reading or writing the key itself.
+    pub fn check_index(mut self, key: &str, index: u64) -> Txn {
+        self.ops.push(TxnOp {
+            KV: TxnKVOp {
+                Verb: "check-index",
+                Key: key.to_owned(),
+                Value: None,
+                Index: Some(index),
+                Session: None,
+            },
+        });
+        self
+    }
+
+    /// Submits the accumulated operations to `/v1/txn`. On success returns
+    /// the `KV` result of every operation, in order; on rollback returns a
+    /// `TxnRolledBack` error identifying the offending operations.
+    pub fn commit<T: DeserializeOwned + Default>(
+        &self,
+        client: &Client,
+        o: Option<&WriteOptions>,
+    ) -> Result<(Vec<KVPair<T>>, WriteMeta)> {
+        let (status, body, meta) = post_raw(
+            "/v1/txn",
+            &self.ops,
+            client,
+            HashMap::new(),
+            o,
+        )?;
+
+        if status.is_success() {
+            let success: TxnSuccess = serde_json::from_str(&body)?;
+            let results = self
+                .ops
+                .iter()
+                .zip(success.Results)
+                .map(|(op, entry)| decode_txn_result(op.KV.Verb, entry.KV))
+                .collect::<Result<Vec<_>>>()?;
+            Ok((results, meta))
+        } else if status == reqwest::StatusCode::CONFLICT {
+            let failure: TxnFailure = serde_json::from_str(&body)?;
+            Err(Error::TxnRolledBack(failure.Errors))
+        } else {
+            Err(Error::from(format!(
+                "unexpected response from /v1/txn: {} {}",
+                status, body
+            )))
+        }
+    }
+}
+
+fn encode_value<T: Serialize>(value: &T) -> Result<String> {
+    Ok(base64::encode(serde_json::to_string(value)?))
+}
+
+/// Decodes a single `Results[].KV` entry according to the verb that
+/// produced it. Only `get`/`set`/`cas` carry a real `Value` for Consul to
+/// echo back; for the other verbs the field is simply absent, which
+/// `#[serde(default)]` fills with `""` — decoding that as JSON would fail
+/// even though the op itself succeeded, so those verbs skip the decode and
+/// get `T::default()` instead.
+fn decode_txn_result<T: DeserializeOwned + Default>(verb: &str, kv: KVResult) -> Result<KVPair<T>> {
+    match verb {
+        "get" | "set" | "cas" => KVPair::try_from(kv),
+        _ => Ok(KVPair {
+            Key: kv.Key,
+            CreateIndex: kv.CreateIndex,
+            ModifyIndex: kv.ModifyIndex,
+            LockIndex: kv.LockIndex,
+            Flags: kv.Flags,
+            Value: Rc::new(T::default()),
+            Session: kv.Session,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_serializes_to_consuls_txn_kv_shape() {
+        let txn = Txn::new().set("foo", &String::from("bar")).unwrap();
+        let value = serde_json::to_value(&txn.ops).unwrap();
+        assert_eq!(value[0]["KV"]["Verb"], "set");
+        assert_eq!(value[0]["KV"]["Key"], "foo");
+        assert_eq!(value[0]["KV"]["Value"], encode_value(&String::from("bar")).unwrap());
+    }
+
+    #[test]
+    fn cas_serializes_the_compare_index() {
+        let txn = Txn::new().cas("foo", &String::from("bar"), 7).unwrap();
+        let value = serde_json::to_value(&txn.ops).unwrap();
+        assert_eq!(value[0]["KV"]["Verb"], "cas");
+        assert_eq!(value[0]["KV"]["Index"], 7);
+    }
+
+    #[test]
+    fn encode_value_errors_instead_of_panicking_on_unserializable_values() {
+        use std::collections::HashMap;
+        // serde_json can't serialize a map with non-string keys, so this
+        // must come back as an `Err`, never a panic.
+        let mut bad_map = HashMap::new();
+        bad_map.insert((1, 2), "unserializable key");
+        assert!(encode_value(&bad_map).is_err());
+    }
+
+    #[test]
+    fn decode_txn_result_defaults_the_value_for_verbs_without_one() {
+        let kv = KVResult {
+            Key: String::from("foo"),
+            CreateIndex: Some(1),
+            ModifyIndex: Some(2),
+            LockIndex: None,
+            Flags: None,
+            Value: String::new(),
+            Session: None,
+        };
+        let pair = decode_txn_result::<String>("delete", kv).unwrap();
+        assert_eq!(*pair.Value, String::default());
+    }
+
+    #[test]
+    fn decode_txn_result_decodes_the_value_for_get() {
+        let kv = KVResult {
+            Key: String::from("foo"),
+            CreateIndex: None,
+            ModifyIndex: None,
+            LockIndex: None,
+            Flags: None,
+            Value: base64::encode("\"bar\""),
+            Session: None,
+        };
+        let pair = decode_txn_result::<String>("get", kv).unwrap();
+        assert_eq!(*pair.Value, "bar");
+    }
+
+    #[test]
+    fn get_omits_value_and_index() {
+        let txn = Txn::new().get("foo");
+        let value = serde_json::to_value(&txn.ops).unwrap();
+        let kv = value[0]["KV"].as_object().unwrap();
+        assert!(!kv.contains_key("Value"));
+        assert!(!kv.contains_key("Index"));
+    }
+
+    #[test]
+    fn decodes_success_response() {
+        let value = encode_value(&String::from("bar")).unwrap();
+        let body = format!(r#"{{"Results":[{{"KV":{{"Key":"foo","Value":"{}"}}}}]}}"#, value);
+        let success: TxnSuccess = serde_json::from_str(&body).unwrap();
+        assert_eq!(success.Results.len(), 1);
+        assert_eq!(success.Results[0].KV.Key, "foo");
+    }
+
+    #[test]
+    fn decodes_rollback_response() {
+        let body = r#"{"Errors":[{"OpIndex":0,"What":"key exists"}]}"#;
+        let failure: TxnFailure = serde_json::from_str(body).unwrap();
+        assert_eq!(failure.Errors.len(), 1);
+        assert_eq!(failure.Errors[0].OpIndex, 0);
+        assert_eq!(failure.Errors[0].What, "key exists");
+    }
+}